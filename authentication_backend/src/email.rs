@@ -0,0 +1,139 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2018 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use authentication_background::{Error as WorkerError, Message};
+pub use authentication_background::InitialConfig;
+use diesel::prelude::*;
+use lettre::EmailTransport;
+use lettre_email::EmailBuilder;
+
+use CONFIG;
+use error::Result;
+use models::user::UserTrait;
+
+pub const SEND_EMAIL_HANDLER: &'static str = "send_email";
+
+const VERIFICATION_EMAIL_SUBJECT: &'static str = "Verify your account";
+const VERIFICATION_EMAIL_TEMPLATE: &'static str =
+    "Welcome! Use this code to verify your account: {{code}}";
+
+/// The payload enqueued onto the background worker for an outgoing email.
+/// `substitutions` are interpolated into `template` as `{{key}}` before the
+/// message is handed to the SMTP transport.
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    to: String,
+    subject: String,
+    template: String,
+    substitutions: HashMap<String, String>,
+}
+
+impl EmailMessage {
+    pub fn new(to: String, subject: String, template: String) -> Self {
+        EmailMessage {
+            to: to,
+            subject: subject,
+            template: template,
+            substitutions: HashMap::new(),
+        }
+    }
+
+    pub fn with_substitution(mut self, key: &str, value: &str) -> Self {
+        self.substitutions.insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    fn render(&self) -> String {
+        let mut body = self.template.clone();
+
+        for (key, value) in &self.substitutions {
+            body = body.replace(&format!("{{{{{}}}}}", key), value);
+        }
+
+        body
+    }
+}
+
+/// Registers the `send_email` handler on the worker's `InitialConfig`. Call
+/// this before `worker::run` so every `EmailMessage` enqueued through a
+/// `Config`'s `hook()` gets delivered over SMTP, retried on transient
+/// failure by the worker itself.
+pub fn register_handler(
+    config: &mut InitialConfig<'static, EmailMessage>,
+) -> Result<(), WorkerError> {
+    config.register_handler(
+        SEND_EMAIL_HANDLER.to_owned(),
+        Arc::new(|message: &Option<EmailMessage>| {
+            let message = match *message {
+                Some(ref message) => message,
+                None => return Err(WorkerError::ProcessingError("missing email payload".to_owned())),
+            };
+
+            send(message).map_err(|e| WorkerError::ProcessingError(e.to_string()))
+        }),
+    )
+}
+
+/// Looks up the verification code created alongside `user` and enqueues the
+/// verification email for delivery through the worker registered with
+/// `register_handler`. Callers hand this the `Config`'s `email_hook()`
+/// transparently, so sign-up never blocks on the SMTP round-trip (or its
+/// retries) itself.
+pub fn enqueue_verification_email<U: UserTrait>(user: &U, to: &str) -> Result<()> {
+    use schema::verification_codes::dsl::{verification_codes, user_id, code};
+
+    let db = CONFIG.db()?;
+
+    let verification_code: String = verification_codes
+        .filter(user_id.eq(user.id()))
+        .select(code)
+        .first(db.conn())?;
+
+    let message = EmailMessage::new(
+        to.to_owned(),
+        VERIFICATION_EMAIL_SUBJECT.to_owned(),
+        VERIFICATION_EMAIL_TEMPLATE.to_owned(),
+    ).with_substitution("code", &verification_code);
+
+    CONFIG
+        .email_hook()
+        .send(Message::new(SEND_EMAIL_HANDLER.to_owned(), Some(message)))
+        .map_err(|_| ::error::Error::IOError)?;
+
+    Ok(())
+}
+
+fn send(message: &EmailMessage) -> Result<(), ::error::Error> {
+    let email = EmailBuilder::new()
+        .to(message.to.as_str())
+        .from(CONFIG.mail_from())
+        .subject(message.subject.as_str())
+        .html(message.render())
+        .build()
+        .map_err(|_| ::error::Error::IOError)?;
+
+    let mut transport = CONFIG.mail_transport()?;
+
+    transport.send(&email).map_err(|_| ::error::Error::IOError)?;
+
+    Ok(())
+}
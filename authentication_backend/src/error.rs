@@ -1,5 +1,6 @@
 use diesel;
 use bcrypt;
+use argon2;
 use std::io;
 use std::result;
 use std::num;
@@ -19,6 +20,11 @@ pub enum Error {
     UserNotVerifiedError,
     InvalidWebtokenError,
     ExpiredWebtokenError,
+    InvalidRefreshTokenError,
+    ExpiredRefreshTokenError,
+    BlockedUser,
+    InvalidInvite,
+    InviteRequired,
     ParseError,
     IOError,
 }
@@ -44,12 +50,44 @@ impl ToString for Error {
             Error::UserNotVerifiedError => "User is not verified".to_string(),
             Error::InvalidWebtokenError => "Webtoken is invalid".to_string(),
             Error::ExpiredWebtokenError => "Webtoken has expired".to_string(),
+            Error::InvalidRefreshTokenError => "Refresh token is invalid".to_string(),
+            Error::ExpiredRefreshTokenError => "Refresh token has expired".to_string(),
+            Error::BlockedUser => "User is blocked".to_string(),
+            Error::InvalidInvite => "Invitation code is invalid or already used".to_string(),
+            Error::InviteRequired => "An invitation is required to sign up".to_string(),
             Error::ParseError => "Could not parse data from string".to_string(),
             Error::IOError => "Something went wrong".to_string(),
         }
     }
 }
 
+impl Error {
+    /// The HTTP status code this error should be reported as. Kept as a bare
+    /// `u16` here rather than a web-framework type since this crate has no
+    /// opinion on which framework ends up serving it.
+    pub fn status_code(&self) -> u16 {
+        match *self {
+            Error::GetDbError => 500,
+            Error::NoResultError => 404,
+            Error::DieselError => 500,
+            Error::PasswordHashError => 500,
+            Error::InvalidPasswordError => 400,
+            Error::InvalidUsernameError => 400,
+            Error::PasswordMatchError => 401,
+            Error::UserNotVerifiedError => 403,
+            Error::InvalidWebtokenError => 401,
+            Error::ExpiredWebtokenError => 401,
+            Error::InvalidRefreshTokenError => 401,
+            Error::ExpiredRefreshTokenError => 401,
+            Error::BlockedUser => 401,
+            Error::InvalidInvite => 400,
+            Error::InviteRequired => 400,
+            Error::ParseError => 400,
+            Error::IOError => 500,
+        }
+    }
+}
+
 impl From<diesel::result::Error> for Error {
     fn from(e: diesel::result::Error) -> Error {
         match e {
@@ -71,6 +109,12 @@ impl From<bcrypt::BcryptError> for Error {
     }
 }
 
+impl From<argon2::Error> for Error {
+    fn from(_: argon2::Error) -> Error {
+        Error::PasswordHashError
+    }
+}
+
 impl From<errors::Error> for Error {
     fn from(e: errors::Error) -> Error {
         match *e.kind() {
@@ -0,0 +1,32 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2018 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use error::Result;
+use models::invitation::Invitation;
+use models::{Admin, UserTrait};
+
+impl Admin {
+    pub fn create_invite(&self, email: Option<String>) -> Result<Invitation> {
+        Invitation::create(UserTrait::id(self), email)
+    }
+
+    pub fn revoke_invite(&self, invitation: &Invitation) -> Result<()> {
+        invitation.revoke()
+    }
+}
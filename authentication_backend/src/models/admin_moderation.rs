@@ -0,0 +1,54 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2018 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use diesel;
+use diesel::prelude::*;
+
+use CONFIG;
+use error::Result;
+use models::refresh_token::RefreshToken;
+use models::{Admin, User, UserTrait};
+
+impl Admin {
+    pub fn block_user(&self, target: &User) -> Result<()> {
+        use schema::users::dsl::*;
+
+        let db = CONFIG.db()?;
+
+        diesel::update(users.filter(id.eq(UserTrait::id(target))))
+            .set(blocked.eq(true))
+            .execute(db.conn())?;
+
+        RefreshToken::delete_all_for_user(UserTrait::id(target))?;
+
+        Ok(())
+    }
+
+    pub fn unblock_user(&self, target: &User) -> Result<()> {
+        use schema::users::dsl::*;
+
+        let db = CONFIG.db()?;
+
+        diesel::update(users.filter(id.eq(UserTrait::id(target))))
+            .set(blocked.eq(false))
+            .execute(db.conn())?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2018 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use rand::{self, Rng};
+
+use CONFIG;
+use ToAuth;
+use error::{Error, Result};
+use models::{User, UserTrait};
+use schema::invitations;
+
+const INVITE_LIFETIME_DAYS: i64 = 14;
+const INVITE_CODE_LENGTH: usize = 32;
+
+#[derive(Debug, Queryable)]
+pub struct Invitation {
+    id: i32,
+    code: String,
+    created_by: i32,
+    email: Option<String>,
+    expires_at: NaiveDateTime,
+    consumed_by: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "invitations"]
+struct NewInvitation {
+    code: String,
+    created_by: i32,
+    email: Option<String>,
+    expires_at: NaiveDateTime,
+}
+
+impl Invitation {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now().naive_utc()
+    }
+
+    pub fn is_consumed(&self) -> bool {
+        self.consumed_by.is_some()
+    }
+
+    pub fn create(created_by: i32, email: Option<String>) -> Result<Self> {
+        let code: String = rand::thread_rng().gen_ascii_chars().take(INVITE_CODE_LENGTH).collect();
+
+        let db = CONFIG.db()?;
+
+        let new_invitation = NewInvitation {
+            code: code,
+            created_by: created_by,
+            email: email,
+            expires_at: Utc::now().naive_utc() + Duration::days(INVITE_LIFETIME_DAYS),
+        };
+
+        let invitation = diesel::insert_into(invitations::table)
+            .values(&new_invitation)
+            .get_result(db.conn())?;
+
+        Ok(invitation)
+    }
+
+    pub fn find_by_code(code: &str) -> Result<Self> {
+        use schema::invitations::dsl::{invitations, code as code_col};
+
+        let db = CONFIG.db()?;
+
+        let invitation = invitations.filter(code_col.eq(code)).first(db.conn())?;
+
+        Ok(invitation)
+    }
+
+    /// A non-mutating check that `code` is valid, unexpired, and unconsumed,
+    /// used to reject a bad code *before* an account is created for it.
+    /// Doesn't itself prevent two concurrent callers both passing this check
+    /// for the same code; `consume` is what actually enforces single-use.
+    pub fn find_valid(code: &str) -> Result<Self> {
+        let invitation = Invitation::find_by_code(code).map_err(|_| Error::InvalidInvite)?;
+
+        if invitation.is_consumed() || invitation.is_expired() {
+            return Err(Error::InvalidInvite);
+        }
+
+        Ok(invitation)
+    }
+
+    pub fn revoke(&self) -> Result<()> {
+        use schema::invitations::dsl::*;
+
+        let db = CONFIG.db()?;
+
+        diesel::delete(invitations.filter(id.eq(self.id))).execute(db.conn())?;
+
+        Ok(())
+    }
+
+    /// Looks up `code` and, if it is valid, unexpired, and unconsumed,
+    /// atomically marks it consumed by `user_id` so it can never be
+    /// redeemed again. Returns `Error::InvalidInvite` for anything else
+    /// (unknown code, expired, already consumed) so callers can't tell
+    /// those cases apart from the response.
+    pub fn consume(code: &str, user_id: i32) -> Result<()> {
+        use schema::invitations::dsl::{invitations, code as code_col, consumed_by, expires_at};
+
+        let db = CONFIG.db()?;
+
+        let now = Utc::now().naive_utc();
+
+        let updated = diesel::update(
+            invitations
+                .filter(code_col.eq(code))
+                .filter(consumed_by.is_null())
+                .filter(expires_at.gt(now)),
+        ).set(consumed_by.eq(user_id))
+            .execute(db.conn())?;
+
+        if updated == 0 {
+            return Err(Error::InvalidInvite);
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates a user, consuming an invitation code for it when
+/// `CONFIG.invite_required()` is on. The code is rejected *before* anything
+/// is persisted, and creation plus consumption run as a single transaction
+/// so a code that loses the single-use race (consumed by a concurrent
+/// request between the pre-check and the atomic update) never leaves behind
+/// an account that was never actually gated.
+pub fn create_user_with_invite<T: ToAuth>(auth: &T, invite_code: Option<&str>) -> Result<User> {
+    if CONFIG.invite_required() {
+        let code = invite_code.ok_or(Error::InviteRequired)?;
+        Invitation::find_valid(code)?;
+    }
+
+    let db = CONFIG.db()?;
+
+    db.conn().transaction(|| {
+        let user = User::create(auth)?;
+
+        if CONFIG.invite_required() {
+            if let Some(code) = invite_code {
+                if let Err(err) = Invitation::consume(code, UserTrait::id(&user)) {
+                    use schema::users::dsl::{users, id};
+
+                    diesel::delete(users.filter(id.eq(UserTrait::id(&user)))).execute(db.conn())?;
+
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(user)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::user::test_helper::with_auth_session;
+
+    #[test]
+    fn consume_is_single_use() {
+        with_auth_session(|auth| {
+            let invitation =
+                Invitation::create(UserTrait::id(&auth), None).expect("Failed to create invitation");
+
+            let first = Invitation::consume(invitation.code(), UserTrait::id(&auth));
+            assert!(first.is_ok(), "Failed to consume a fresh invitation");
+
+            let second = Invitation::consume(invitation.code(), UserTrait::id(&auth));
+            assert!(!second.is_ok(), "Invitation was consumed more than once");
+        });
+    }
+
+    #[test]
+    fn find_valid_rejects_an_already_consumed_code() {
+        with_auth_session(|auth| {
+            let invitation =
+                Invitation::create(UserTrait::id(&auth), None).expect("Failed to create invitation");
+
+            Invitation::consume(invitation.code(), UserTrait::id(&auth))
+                .expect("Failed to consume a fresh invitation");
+
+            let result = Invitation::find_valid(invitation.code());
+
+            assert!(!result.is_ok(), "Consumed invitation was still considered valid");
+        });
+    }
+}
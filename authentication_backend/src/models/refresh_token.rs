@@ -0,0 +1,129 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2018 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+
+use CONFIG;
+use error::Result;
+use schema::refresh_tokens;
+
+const REFRESH_TOKEN_LIFETIME_DAYS: i64 = 30;
+
+#[derive(Debug, Queryable)]
+pub struct RefreshToken {
+    id: i32,
+    user_id: i32,
+    token_hash: String,
+    expires_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+    consumed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[table_name = "refresh_tokens"]
+struct NewRefreshToken {
+    user_id: i32,
+    token_hash: String,
+    expires_at: NaiveDateTime,
+}
+
+impl RefreshToken {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn user_id(&self) -> i32 {
+        self.user_id
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now().naive_utc()
+    }
+
+    /// A token that was already rotated away is kept around (instead of being
+    /// dropped outright) so a replay of it can be told apart from a token
+    /// that never existed, which is what lets `Webtoken::refresh` treat reuse
+    /// as theft rather than a plain invalid-token error.
+    pub fn is_consumed(&self) -> bool {
+        self.consumed_at.is_some()
+    }
+
+    pub fn find_by_hash(hash: &str) -> Result<Option<Self>> {
+        use schema::refresh_tokens::dsl::*;
+
+        let db = CONFIG.db()?;
+
+        let found = refresh_tokens
+            .filter(token_hash.eq(hash))
+            .first(db.conn())
+            .optional()?;
+
+        Ok(found)
+    }
+
+    pub fn create(user_id: i32, hash: String) -> Result<Self> {
+        let db = CONFIG.db()?;
+
+        let new_token = NewRefreshToken {
+            user_id: user_id,
+            token_hash: hash,
+            expires_at: Utc::now().naive_utc() + Duration::days(REFRESH_TOKEN_LIFETIME_DAYS),
+        };
+
+        let token = diesel::insert_into(refresh_tokens::table)
+            .values(&new_token)
+            .get_result(db.conn())?;
+
+        Ok(token)
+    }
+
+    pub fn delete(&self) -> Result<()> {
+        use schema::refresh_tokens::dsl::*;
+
+        let db = CONFIG.db()?;
+
+        diesel::delete(refresh_tokens.filter(id.eq(self.id))).execute(db.conn())?;
+
+        Ok(())
+    }
+
+    pub fn mark_consumed(&self) -> Result<()> {
+        use schema::refresh_tokens::dsl::*;
+
+        let db = CONFIG.db()?;
+
+        diesel::update(refresh_tokens.filter(id.eq(self.id)))
+            .set(consumed_at.eq(Utc::now().naive_utc()))
+            .execute(db.conn())?;
+
+        Ok(())
+    }
+
+    pub fn delete_all_for_user(target_user_id: i32) -> Result<()> {
+        use schema::refresh_tokens::dsl::*;
+
+        let db = CONFIG.db()?;
+
+        diesel::delete(refresh_tokens.filter(user_id.eq(target_user_id))).execute(db.conn())?;
+
+        Ok(())
+    }
+}
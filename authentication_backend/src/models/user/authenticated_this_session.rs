@@ -19,9 +19,10 @@
 
 use diesel;
 use diesel::prelude::*;
-use bcrypt::hash;
 use CONFIG;
 use error::{Error, Result};
+use models::refresh_token::RefreshToken;
+use password_hasher::{self, needs_rehash};
 use webtoken::Webtoken;
 use super::{UserTrait, User, Authenticated};
 use super::helpers::{validate_username, validate_password};
@@ -56,6 +57,8 @@ impl AuthenticatedThisSession {
         diesel::delete(users.filter(username.eq(&self.username)))
             .execute(db.conn())?;
 
+        RefreshToken::delete_all_for_user(self.id)?;
+
         Ok(())
     }
 
@@ -89,7 +92,7 @@ impl AuthenticatedThisSession {
 
         let new_pass = validate_password(new_pass)?;
 
-        let hash = hash(new_pass, CONFIG.bcrypt_cost())?;
+        let hash = CONFIG.password_hasher().hash(new_pass)?;
 
         let db = CONFIG.db()?;
 
@@ -113,11 +116,24 @@ impl AuthenticatedThisSession {
 
         let user: User = users.filter(username.eq(uname)).first(db.conn())?;
 
-        if user.verify_password(pword)? {
-            Ok(AuthenticatedThisSession::from_user(&user))
-        } else {
-            Err(Error::PasswordMatchError)
+        if user.is_blocked() {
+            return Err(Error::BlockedUser);
+        }
+
+        if !password_hasher::verify_any(pword, user.password_hash())? {
+            return Err(Error::PasswordMatchError);
         }
+
+        let hasher = CONFIG.password_hasher();
+        if needs_rehash(user.password_hash(), &*hasher) {
+            let rehashed = hasher.hash(pword)?;
+
+            diesel::update(users.filter(id.eq(UserTrait::id(&user))))
+                .set(password.eq(&rehashed))
+                .execute(db.conn())?;
+        }
+
+        Ok(AuthenticatedThisSession::from_user(&user))
     }
 
     pub fn verify(&mut self) -> bool {
@@ -131,7 +147,11 @@ impl AuthenticatedThisSession {
     }
 
     fn from_authenticated(auth: &Authenticated, password: &str) -> Result<Self> {
-        if auth.verify_password(password)? {
+        if auth.is_blocked() {
+            return Err(Error::BlockedUser);
+        }
+
+        if password_hasher::verify_any(password, auth.password_hash())? {
             Ok(AuthenticatedThisSession {
                 id: auth.id(),
                 username: auth.username().to_owned(),
@@ -224,6 +244,28 @@ mod tests {
         });
     }
 
+    #[test]
+    fn blocked_user_cannot_log_in_with_username_and_password() {
+        with_auth_session(|auth| {
+            use schema::users::dsl::*;
+
+            let user = User::find_by_id(auth.id()).expect("Failed to find user for auth_session");
+
+            diesel::update(users.filter(id.eq(UserTrait::id(&user))))
+                .set(blocked.eq(true))
+                .execute(CONFIG.db().unwrap().conn())
+                .expect("Failed to block user");
+
+            let result =
+                AuthenticatedThisSession::from_username_and_password(user.username(), "wrong");
+
+            match result {
+                Err(Error::BlockedUser) => {}
+                _ => panic!("Blocked user was not rejected with Error::BlockedUser"),
+            }
+        });
+    }
+
     #[test]
     fn delete_deletes_associated_verification_code() {
         with_auth_session(|auth_session| {
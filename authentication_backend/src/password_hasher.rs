@@ -0,0 +1,117 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2018 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use argon2;
+use bcrypt;
+use rand::{self, Rng};
+
+use error::Result;
+
+const ARGON2_MEMORY_COST_KIB: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+
+/// Something that can turn a plaintext password into a stored hash and back
+/// again. Implementations are expected to produce self-describing strings
+/// (bcrypt's `$2b$` prefix, Argon2's `$argon2id$` prefix) so `verify_any`
+/// below can dispatch on the stored value alone, independent of whichever
+/// hasher is currently configured as the default.
+pub trait PasswordHasher: Send + Sync {
+    fn hash(&self, plaintext: &str) -> Result<String>;
+    fn verify(&self, plaintext: &str, stored: &str) -> Result<bool>;
+
+    /// The prefix this hasher's own output always starts with, e.g. `$2b$`
+    /// or `$argon2id$`. Used to tell whether an existing stored hash already
+    /// matches the currently configured algorithm.
+    fn prefix(&self) -> &'static str;
+}
+
+pub struct BcryptHasher {
+    cost: u32,
+}
+
+impl BcryptHasher {
+    pub fn new(cost: u32) -> Self {
+        BcryptHasher { cost: cost }
+    }
+}
+
+impl PasswordHasher for BcryptHasher {
+    fn hash(&self, plaintext: &str) -> Result<String> {
+        Ok(bcrypt::hash(plaintext, self.cost)?)
+    }
+
+    fn verify(&self, plaintext: &str, stored: &str) -> Result<bool> {
+        Ok(bcrypt::verify(plaintext, stored)?)
+    }
+
+    fn prefix(&self) -> &'static str {
+        "$2b$"
+    }
+}
+
+pub struct Argon2Hasher;
+
+impl Argon2Hasher {
+    fn config() -> argon2::Config<'static> {
+        let mut config = argon2::Config::default();
+        config.variant = argon2::Variant::Argon2id;
+        config.mem_cost = ARGON2_MEMORY_COST_KIB;
+        config.time_cost = ARGON2_ITERATIONS;
+        config.lanes = ARGON2_PARALLELISM;
+        config
+    }
+}
+
+impl PasswordHasher for Argon2Hasher {
+    fn hash(&self, plaintext: &str) -> Result<String> {
+        let salt: Vec<u8> = rand::thread_rng().gen_iter().take(16).collect();
+
+        let encoded = argon2::hash_encoded(plaintext.as_bytes(), &salt, &Argon2Hasher::config())?;
+
+        Ok(encoded)
+    }
+
+    fn verify(&self, plaintext: &str, stored: &str) -> Result<bool> {
+        Ok(argon2::verify_encoded(stored, plaintext.as_bytes())?)
+    }
+
+    fn prefix(&self) -> &'static str {
+        "$argon2id$"
+    }
+}
+
+/// Verifies `plaintext` against `stored`, picking the hasher implied by
+/// `stored`'s own prefix rather than whatever `CONFIG` currently treats as
+/// the default. This is what lets the default change (bcrypt -> Argon2id)
+/// without invalidating every password hashed under the old one.
+pub fn verify_any(plaintext: &str, stored: &str) -> Result<bool> {
+    if stored.starts_with(Argon2Hasher.prefix()) {
+        Argon2Hasher.verify(plaintext, stored)
+    } else {
+        BcryptHasher::new(bcrypt::DEFAULT_COST).verify(plaintext, stored)
+    }
+}
+
+/// True when `stored` was not produced by the currently configured hasher,
+/// meaning the caller should re-hash and persist the plaintext under the
+/// current scheme now that it has been verified.
+pub fn needs_rehash(stored: &str, current: &PasswordHasher) -> bool {
+    !stored.starts_with(current.prefix())
+}
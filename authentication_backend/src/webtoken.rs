@@ -0,0 +1,203 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2017 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use chrono::{Duration, Utc};
+use diesel;
+use diesel::prelude::*;
+use jwt::{encode, Header};
+use rand::{self, Rng};
+use sha2::{Digest, Sha256};
+
+use CONFIG;
+use error::{Error, Result};
+use models::User;
+use models::refresh_token::RefreshToken;
+use models::user::UserTrait;
+
+const ACCESS_TOKEN_LIFETIME_MINUTES: i64 = 15;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: i32,
+    username: String,
+    verified: bool,
+    exp: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Webtoken {
+    token: String,
+    refresh_token: String,
+}
+
+impl Webtoken {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn refresh_token(&self) -> &str {
+        &self.refresh_token
+    }
+
+    pub fn create<U: UserTrait>(user: &U) -> Result<Self> {
+        let token = encode_access_token(user)?;
+        let (raw_refresh_token, hashed) = generate_refresh_token();
+
+        let _ = RefreshToken::create(user.id(), hashed)?;
+
+        Ok(Webtoken {
+            token: token,
+            refresh_token: raw_refresh_token,
+        })
+    }
+
+    /// Exchanges a presented refresh token for a brand new access+refresh
+    /// pair, rotating the old refresh token out in the process. Refresh
+    /// tokens are single-use: presenting one that has already been consumed
+    /// is treated as a sign the token was stolen, so every refresh token
+    /// belonging to that user is revoked. A blocked user's tokens are
+    /// revoked and rejected the same way, independent of whether
+    /// `Admin::block_user` already managed to delete them itself.
+    ///
+    /// The lookup, consumed/expired checks, and the consuming update all run
+    /// inside one transaction with the row locked (`SELECT ... FOR UPDATE`),
+    /// so two requests racing to redeem the same refresh token can't both
+    /// observe it as unconsumed and both rotate it through.
+    pub fn refresh(presented_refresh_token: &str) -> Result<Self> {
+        use schema::refresh_tokens::dsl::{refresh_tokens, token_hash, id, user_id, consumed_at};
+
+        let hashed = hash_refresh_token(presented_refresh_token);
+
+        let db = CONFIG.db()?;
+
+        let user = db.conn().transaction(|| -> Result<User> {
+            let existing: Option<RefreshToken> = refresh_tokens
+                .filter(token_hash.eq(&hashed))
+                .for_update()
+                .first(db.conn())
+                .optional()?;
+
+            let existing = existing.ok_or(Error::InvalidRefreshTokenError)?;
+
+            if existing.is_consumed() {
+                // Deletes on `db.conn()` rather than going through
+                // `RefreshToken::delete_all_for_user`, which would check out
+                // its own connection from the pool and could deadlock
+                // against the `FOR UPDATE` lock this transaction is already
+                // holding on the same row.
+                diesel::delete(refresh_tokens.filter(user_id.eq(existing.user_id())))
+                    .execute(db.conn())?;
+                return Err(Error::InvalidRefreshTokenError);
+            }
+
+            if existing.is_expired() {
+                diesel::delete(refresh_tokens.filter(id.eq(existing.id()))).execute(db.conn())?;
+                return Err(Error::ExpiredRefreshTokenError);
+            }
+
+            let user = User::find_by_id(existing.user_id())?;
+
+            if user.is_blocked() {
+                diesel::delete(refresh_tokens.filter(user_id.eq(existing.user_id())))
+                    .execute(db.conn())?;
+                return Err(Error::BlockedUser);
+            }
+
+            diesel::update(refresh_tokens.filter(id.eq(existing.id())))
+                .set(consumed_at.eq(Utc::now().naive_utc()))
+                .execute(db.conn())?;
+
+            Ok(user)
+        })?;
+
+        Webtoken::create(&user)
+    }
+}
+
+fn encode_access_token<U: UserTrait>(user: &U) -> Result<String> {
+    let claims = Claims {
+        sub: user.id(),
+        username: user.username().to_owned(),
+        verified: user.is_verified(),
+        exp: (Utc::now() + Duration::minutes(ACCESS_TOKEN_LIFETIME_MINUTES)).timestamp(),
+    };
+
+    let token = encode(&Header::default(), &claims, CONFIG.secret().as_ref())?;
+
+    Ok(token)
+}
+
+fn generate_refresh_token() -> (String, String) {
+    let raw: String = rand::thread_rng()
+        .gen_ascii_chars()
+        .take(64)
+        .collect();
+
+    let hashed = hash_refresh_token(&raw);
+
+    (raw, hashed)
+}
+
+fn hash_refresh_token(raw: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(raw.as_bytes());
+    format!("{:x}", hasher.result())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::user::test_helper::with_auth_session;
+
+    #[test]
+    fn refresh_rotates_the_token() {
+        with_auth_session(|mut auth| {
+            auth.verify();
+            let token = auth.create_webtoken().expect("Failed to create webtoken");
+
+            let refreshed = Webtoken::refresh(token.refresh_token());
+
+            assert!(refreshed.is_ok(), "Failed to refresh a valid token");
+        });
+    }
+
+    #[test]
+    fn replaying_a_consumed_refresh_token_is_rejected_as_theft() {
+        with_auth_session(|mut auth| {
+            auth.verify();
+            let token = auth.create_webtoken().expect("Failed to create webtoken");
+
+            let refreshed = Webtoken::refresh(token.refresh_token())
+                .expect("Failed to refresh a valid token");
+
+            // The original token was already rotated away; replaying it is
+            // treated as theft and revokes every refresh token for the user,
+            // including the one that was just legitimately issued above.
+            match Webtoken::refresh(token.refresh_token()) {
+                Err(Error::InvalidRefreshTokenError) => {}
+                _ => panic!("Replayed refresh token was not rejected"),
+            }
+
+            match Webtoken::refresh(refreshed.refresh_token()) {
+                Err(Error::InvalidRefreshTokenError) => {}
+                _ => panic!("Refresh token survived a theft-detection revocation"),
+            }
+        });
+    }
+}
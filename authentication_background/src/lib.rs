@@ -17,25 +17,41 @@
  * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+#[macro_use]
+extern crate log;
+
 use std::collections::HashMap;
 use std::thread;
 use std::sync::{Arc, mpsc};
 use std::any::Any;
 use std::fmt;
 use std::error::Error as StdError;
+use std::time::Duration;
+
+const DEFAULT_RETRIES: i32 = 10;
+
+pub const DEAD_LETTER_HANDLER: &'static str = "dead_letter";
 
 pub struct Message<T: Send + Sync> {
     name: String,
     message: Option<T>,
     retries: i32,
+    initial_retries: i32,
+    origin_name: Option<String>,
 }
 
 impl<T: Send + Sync> Message<T> {
     pub fn new(name: String, message: Option<T>) -> Message<T> {
+        Message::with_retries(name, message, DEFAULT_RETRIES)
+    }
+
+    pub fn with_retries(name: String, message: Option<T>, retries: i32) -> Message<T> {
         Message::<T> {
             name: name,
             message: message,
-            retries: 10,
+            retries: retries,
+            initial_retries: retries,
+            origin_name: None,
         }
     }
 
@@ -50,6 +66,32 @@ impl<T: Send + Sync> Message<T> {
     pub fn retries(&self) -> i32 {
         self.retries
     }
+
+    /// The name of the message that originally failed, set when this
+    /// `Message` was constructed as a dead-letter envelope for it.
+    pub fn origin_name(&self) -> Option<&str> {
+        self.origin_name.as_ref().map(String::as_str)
+    }
+
+    fn requeued(self) -> Message<T> {
+        Message {
+            name: self.name,
+            message: self.message,
+            retries: self.retries - 1,
+            initial_retries: self.initial_retries,
+            origin_name: self.origin_name,
+        }
+    }
+
+    fn into_dead_letter(self) -> Message<T> {
+        Message {
+            name: DEAD_LETTER_HANDLER.to_owned(),
+            message: self.message,
+            retries: 0,
+            initial_retries: 0,
+            origin_name: Some(self.name),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -57,6 +99,7 @@ pub enum Error {
     ProcessingError(String),
     DuplicateHandler(String),
     ExitHandler,
+    DeadLetterHandler,
     SendError,
     JoinError,
 }
@@ -67,6 +110,7 @@ impl StdError for Error {
             Error::ProcessingError(_) => "Error processing job",
             Error::DuplicateHandler(_) => "Handler with that name already exists",
             Error::ExitHandler => "Cannot register handler with reserved anme 'exit'",
+            Error::DeadLetterHandler => "Cannot register handler with reserved name 'dead_letter'",
             Error::SendError => "Could not send data",
             Error::JoinError => "Could not join thread",
         }
@@ -83,6 +127,9 @@ impl fmt::Display for Error {
             Error::ProcessingError(ref s) => write!(f, "Error processing data: '{}'", s),
             Error::DuplicateHandler(ref s) => write!(f, "Handler already exists for '{}'", s),
             Error::ExitHandler => write!(f, "Cannot register handler with reserved name 'exit'"),
+            Error::DeadLetterHandler => {
+                write!(f, "Cannot register handler with reserved name 'dead_letter'")
+            }
             Error::SendError => write!(f, "Could not send data to thread"),
             Error::JoinError => write!(f, "Could not join thread"),
         }
@@ -110,11 +157,27 @@ where
     T: 'a,
 {
     handlers: HashMap<String, SafeHandler<'a, T>>,
+    base_delay: Duration,
+    max_delay: Duration,
 }
 
 impl<'a, T: Send + Sync> InitialConfig<'a, T> {
     pub fn new() -> Self {
-        InitialConfig { handlers: HashMap::new() }
+        InitialConfig {
+            handlers: HashMap::new(),
+            base_delay: Duration::from_secs(0),
+            max_delay: Duration::from_secs(0),
+        }
+    }
+
+    /// `base_delay` is the delay before the first retry; each subsequent
+    /// retry doubles it, capped at `max_delay`. The delay is computed from
+    /// the decremented retry count carried on the `Message` itself, so the
+    /// scheduler doesn't need to track anything between requeues.
+    pub fn with_backoff(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        self
     }
 
     pub fn register_handler(
@@ -126,6 +189,10 @@ impl<'a, T: Send + Sync> InitialConfig<'a, T> {
             return Err(Error::ExitHandler);
         }
 
+        if &name == DEAD_LETTER_HANDLER {
+            return Err(Error::DeadLetterHandler);
+        }
+
         if self.handlers.contains_key(&name) {
             return Err(Error::DuplicateHandler(name));
         };
@@ -147,12 +214,29 @@ impl<T: Send + Sync> Config<T> {
     }
 }
 
+fn backoff_delay(base_delay: Duration, max_delay: Duration, initial_retries: i32, retries: i32) -> Duration {
+    if base_delay == Duration::from_secs(0) {
+        return base_delay;
+    }
+
+    let attempt = (initial_retries - retries).max(0) as u32;
+    let factor = 2u32.checked_pow(attempt).unwrap_or(u32::max_value());
+
+    let delay = base_delay * factor;
+
+    if max_delay > Duration::from_secs(0) && delay > max_delay {
+        max_delay
+    } else {
+        delay
+    }
+}
+
 pub fn run<'a, T: Send + Sync + Clone>(config: InitialConfig<'static, T>) -> Config<T> {
     let (hook, receiver) = mpsc::channel::<Message<T>>();
     let thread_hook = hook.clone();
 
     let thread = thread::spawn(move || {
-        let InitialConfig { handlers } = config.clone();
+        let InitialConfig { handlers, base_delay, max_delay } = config.clone();
 
         for msg in receiver {
             if msg.name() == "exit" {
@@ -162,31 +246,46 @@ pub fn run<'a, T: Send + Sync + Clone>(config: InitialConfig<'static, T>) -> Con
             let handler = match handlers.get(msg.name()) {
                 Some(ref handler) => *handler,
                 None => {
-                    println!("No handler for message '{}'", msg.name());
+                    warn!("No handler for message '{}'", msg.name());
                     continue;
                 }
             };
 
             if let Err(err) = handler(msg.message()) {
                 if msg.retries > 0 {
-                    println!(
-                        "Task for '{}' failed with error: '{}', retrying",
+                    let delay = backoff_delay(base_delay, max_delay, msg.initial_retries, msg.retries);
+
+                    warn!(
+                        "Task for '{}' failed with error: '{}', retrying in {:?}",
                         msg.name(),
-                        err
+                        err,
+                        delay
                     );
+
+                    if delay > Duration::from_secs(0) {
+                        thread::sleep(delay);
+                    }
+
                     thread_hook
-                        .send(Message {
-                            name: msg.name,
-                            message: msg.message,
-                            retries: msg.retries - 1,
-                        })
+                        .send(msg.requeued())
                         .expect("Failed to requeue task");
                 } else {
-                    println!(
+                    error!(
                         "Task for '{}' failed permanently with error: '{}'",
                         msg.name(),
                         err
                     );
+
+                    if msg.name() != DEAD_LETTER_HANDLER && handlers.contains_key(DEAD_LETTER_HANDLER) {
+                        thread_hook
+                            .send(msg.into_dead_letter())
+                            .expect("Failed to dispatch dead letter");
+                    } else if msg.name() == DEAD_LETTER_HANDLER {
+                        error!(
+                            "Dead letter handler itself failed permanently; dropping task originally for '{}'",
+                            msg.origin_name().unwrap_or("unknown")
+                        );
+                    }
                 }
             };
         }
@@ -203,11 +302,7 @@ pub fn run<'a, T: Send + Sync + Clone>(config: InitialConfig<'static, T>) -> Con
 pub fn cleanup<T: Send + Sync>(config: Config<T>) -> Result<(), Error> {
     let Config { handle, hook } = config;
 
-    hook.send(Message {
-        name: "exit".to_owned(),
-        message: None,
-        retries: 0,
-    })?;
+    hook.send(Message::with_retries("exit".to_owned(), None, 0))?;
 
     handle.join()?;
 
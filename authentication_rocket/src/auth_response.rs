@@ -0,0 +1,67 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2017 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io::Cursor;
+
+use rocket::http::{ContentType, Status};
+use rocket::response::{Responder, Response};
+use rocket::request::Request;
+use serde::Serialize;
+use serde_json;
+
+/// The successful-response envelope for every route in `controllers`: a
+/// human-readable `message` plus whatever `data` the route wants to hand
+/// back, serialized up front so `Response` (see `routes.rs`) stays a plain,
+/// non-generic `Result` alias instead of one generic per payload type.
+pub struct AuthResponse {
+    message: String,
+    data: Option<serde_json::Value>,
+}
+
+impl AuthResponse {
+    pub fn new<T: Serialize>(message: &str, data: T) -> Self {
+        AuthResponse {
+            message: message.to_owned(),
+            data: serde_json::to_value(data).ok(),
+        }
+    }
+
+    pub fn empty(message: &str) -> Self {
+        AuthResponse {
+            message: message.to_owned(),
+            data: None,
+        }
+    }
+}
+
+impl<'r> Responder<'r> for AuthResponse {
+    fn respond_to(self, _: &Request) -> Result<Response<'r>, Status> {
+        let body = json!({
+            "status": Status::Ok.code,
+            "message": self.message,
+            "data": self.data,
+        });
+
+        Response::build()
+            .status(Status::Ok)
+            .header(ContentType::JSON)
+            .sized_body(Cursor::new(serde_json::to_string(&body).unwrap_or_default()))
+            .ok()
+    }
+}
@@ -18,19 +18,66 @@
  */
 
 use authentication_backend::Error as BackendError;
-use authentication_backend::{ToAuth, Admin, User, UserTrait};
+use authentication_backend::{ToAuth, Admin, Authenticated, Invitation, User, UserTrait, Webtoken};
+use authentication_backend::{create_user_with_invite, email};
 use routes::Response;
 use auth_response::AuthResponse;
 
-pub fn sign_up<T>(auth: &T) -> Response
+/// `User::authenticate` only checks the webtoken is valid; it doesn't know
+/// about blocking. Every route that authenticates this way (rather than
+/// through `authenticate_session`, which already rejects blocked users)
+/// needs to call this before trusting the result.
+fn require_not_blocked(user: Authenticated) -> Result<Authenticated, BackendError> {
+    if user.is_blocked() {
+        return Err(BackendError::BlockedUser);
+    }
+
+    Ok(user)
+}
+
+pub fn sign_up<T>(invite_code: Option<&str>, auth: &T) -> Response
 where
     T: ToAuth,
 {
-    let user = User::create(auth)?;
+    let user = create_user_with_invite(auth, invite_code)?;
+
+    if let Err(err) = email::enqueue_verification_email(&user, auth.email()) {
+        warn!(
+            "Failed to enqueue verification email for '{}': {}",
+            user.username(),
+            err.to_string()
+        );
+    }
 
     Ok(AuthResponse::new("User created", user))
 }
 
+pub fn create_invite<T>(email: Option<&str>, auth: &T) -> Response
+where
+    T: ToAuth,
+{
+    let user = require_not_blocked(User::authenticate(auth)?)?;
+    let admin = Admin::from_authenticated(user)?;
+
+    let invitation = admin.create_invite(email.map(str::to_owned))?;
+
+    Ok(AuthResponse::new("Invitation created", invitation.code().to_owned()))
+}
+
+pub fn revoke_invite<T>(code: &str, auth: &T) -> Response
+where
+    T: ToAuth,
+{
+    let user = require_not_blocked(User::authenticate(auth)?)?;
+    let admin = Admin::from_authenticated(user)?;
+
+    let invitation = Invitation::find_by_code(code)?;
+
+    admin.revoke_invite(&invitation)?;
+
+    Ok(AuthResponse::empty("Invitation revoked"))
+}
+
 pub fn log_in<T>(auth: &T) -> Response
 where
     T: ToAuth,
@@ -42,11 +89,17 @@ where
     Ok(AuthResponse::new("Authenticated", token))
 }
 
+pub fn refresh(refresh_token: &str) -> Response {
+    let token = Webtoken::refresh(refresh_token)?;
+
+    Ok(AuthResponse::new("Token refreshed", Some(token)))
+}
+
 pub fn is_authenticated<T>(auth: &T) -> Response
 where
     T: ToAuth,
 {
-    User::authenticate(auth)?;
+    require_not_blocked(User::authenticate(auth)?)?;
 
     Ok(AuthResponse::empty("Authenticated"))
 }
@@ -72,7 +125,7 @@ pub fn grant_permission<T>(target_user: &str, permission: &str, auth: &T) -> Res
 where
     T: ToAuth,
 {
-    let user = User::authenticate(auth)?;
+    let user = require_not_blocked(User::authenticate(auth)?)?;
     let admin = Admin::from_authenticated(user)?;
 
     let target_user = User::find_by_name(&target_user)?;
@@ -86,7 +139,7 @@ pub fn revoke_permission<T>(target_user: &str, permission: &str, auth: &T) -> Re
 where
     T: ToAuth,
 {
-    let user = User::authenticate(auth)?;
+    let user = require_not_blocked(User::authenticate(auth)?)?;
     let admin = Admin::from_authenticated(user)?;
 
     let target_user = User::find_by_name(&target_user)?;
@@ -94,4 +147,32 @@ where
     admin.revoke_permission(&target_user, &permission)?;
 
     Ok(AuthResponse::empty("Permission revoked"))
+}
+
+pub fn block_user<T>(target_user: &str, auth: &T) -> Response
+where
+    T: ToAuth,
+{
+    let user = require_not_blocked(User::authenticate(auth)?)?;
+    let admin = Admin::from_authenticated(user)?;
+
+    let target_user = User::find_by_name(&target_user)?;
+
+    admin.block_user(&target_user)?;
+
+    Ok(AuthResponse::empty("User blocked"))
+}
+
+pub fn unblock_user<T>(target_user: &str, auth: &T) -> Response
+where
+    T: ToAuth,
+{
+    let user = require_not_blocked(User::authenticate(auth)?)?;
+    let admin = Admin::from_authenticated(user)?;
+
+    let target_user = User::find_by_name(&target_user)?;
+
+    admin.unblock_user(&target_user)?;
+
+    Ok(AuthResponse::empty("User unblocked"))
 }
\ No newline at end of file
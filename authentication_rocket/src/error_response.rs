@@ -0,0 +1,63 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2018 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::io::Cursor;
+
+use authentication_backend::Error as BackendError;
+use rocket::http::{ContentType, Status};
+use rocket::response::{Responder, Response};
+use rocket::request::Request;
+use serde_json;
+
+/// Wraps a backend `Error` so it can be returned directly from a route,
+/// rendering as a JSON body with the status code the error maps to instead
+/// of every failure looking like every other one to the client.
+pub struct ErrorResponse(pub BackendError);
+
+impl From<BackendError> for ErrorResponse {
+    fn from(err: BackendError) -> ErrorResponse {
+        ErrorResponse(err)
+    }
+}
+
+impl<'r> Responder<'r> for ErrorResponse {
+    fn respond_to(self, _: &Request) -> Result<Response<'r>, Status> {
+        let status = Status::from_code(self.0.status_code()).unwrap_or(Status::InternalServerError);
+
+        // `BlockedUser` carries its own distinct message so admins can tell it
+        // apart from a bad password in logs, but the client still shouldn't
+        // learn that the account exists and is blocked rather than just
+        // rejected, so it gets normalized to the same message here.
+        let message = match self.0 {
+            BackendError::BlockedUser => BackendError::PasswordMatchError.to_string(),
+            ref other => other.to_string(),
+        };
+
+        let body = json!({
+            "status": status.code,
+            "message": message,
+        });
+
+        Response::build()
+            .status(status)
+            .header(ContentType::JSON)
+            .sized_body(Cursor::new(serde_json::to_string(&body).unwrap_or_default()))
+            .ok()
+    }
+}
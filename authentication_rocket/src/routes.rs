@@ -0,0 +1,28 @@
+/*
+ * This file is part of Authentication.
+ *
+ * Copyright © 2017 Riley Trautman
+ *
+ * Authentication is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * Authentication is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Authentication.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use auth_response::AuthResponse;
+use error_response::ErrorResponse;
+
+/// What every route in `controllers` returns: `AuthResponse` renders as a
+/// 200 with a JSON envelope, `ErrorResponse` renders with whatever status
+/// code the underlying `authentication_backend::Error` maps to. `?` on a
+/// `BackendError` converts into `ErrorResponse` via its `From` impl, so
+/// routes never need to build either side of this by hand.
+pub type Response = Result<AuthResponse, ErrorResponse>;